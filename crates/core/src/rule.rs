@@ -1,8 +1,30 @@
 use crate::meta_var::MetaVarEnv;
 use crate::Node;
 use crate::Pattern;
+use std::collections::HashMap;
 use std::collections::VecDeque;
 
+/// Resolves a node to the symbol it denotes, independent of spelling. This
+/// is the extension point for resolution-aware matching: `match_node` and
+/// `find_node` thread an optional `Resolver` through every combinator so it
+/// reaches whichever matcher needs it, but comparing by resolved symbol
+/// instead of by text is up to that matcher. `Resolved` matches anything
+/// resolvable at all; `SameSymbol` compares two nodes' resolved symbols for
+/// equality, which is what lets `foo()` and `bar::foo()` match as "the same
+/// call" when they denote the same declaration, regardless of spelling.
+/// `Pattern`'s own leaf-identifier matching still compares by spelling, and
+/// `And` requires both sides to match, so pairing a `Pattern` with a
+/// `SameSymbol` narrows a spelling match to one symbol but does not widen it
+/// to other spellings; only a `SameSymbol` on its own matches regardless of
+/// spelling.
+pub trait Resolver {
+    fn resolve<'tree>(&self, node: Node<'tree>) -> Option<SymbolId>;
+}
+
+/// Opaque identifier for a resolved declaration, as produced by a [`Resolver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(pub usize);
+
 /**
  * N.B. At least one positive term is required for matching
  */
@@ -11,29 +33,247 @@ pub trait Matcher {
         &self,
         _node: Node<'tree>,
         _env: &mut MetaVarEnv<'tree>,
+        _resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>>;
 
     fn find_node<'tree>(
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
-        self.match_node(node, env)
-            .or_else(|| node.children().find_map(|sub| self.find_node(sub, env)))
+        self.match_node(node, env, resolver).or_else(|| {
+            node.children()
+                .find_map(|sub| self.find_node(sub, env, resolver))
+        })
+    }
+
+    /// The tree-sitter kind(s) of this matcher's outermost required node,
+    /// when known ahead of time. `find_node_vec` uses this to skip a full
+    /// `match_node` attempt on candidates whose kind can't possibly match.
+    /// Returns `None` when no kind can be pinned down (e.g. a bare metavar),
+    /// in which case every candidate is still tried.
+    fn root_kind(&self) -> Option<Vec<&'static str>> {
+        None
     }
 
-    fn find_node_vec<'tree>(&self, node: Node<'tree>) -> Vec<Node<'tree>> {
+    /// Collect every match under `node`, then prune matches that are fully
+    /// nested inside another match so bulk rewriting never produces
+    /// conflicting edits. A nested match is kept only when it sits inside a
+    /// region the outer match captured as a metavar placeholder, since that
+    /// hole is expected to contain further matches.
+    fn find_node_vec<'tree>(
+        &self,
+        node: Node<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Vec<NodeMatch<'tree>> {
+        let expected_kind = self.root_kind();
         let mut ret = vec![];
-        let mut queue = VecDeque::new();
-        queue.push_back(node);
-        while let Some(cand) = queue.pop_front() {
-            queue.extend(cand.children());
+        for cand in std::iter::once(node).chain(Descendants::new(node)) {
+            if let Some(kinds) = &expected_kind {
+                if !kinds.iter().any(|k| *k == cand.kind()) {
+                    continue;
+                }
+            }
             let mut env = MetaVarEnv::new();
-            if let Some(matched) = self.match_node(cand, &mut env) {
-                ret.push(matched);
+            if let Some(matched) = self.match_node(cand, &mut env, resolver) {
+                ret.push(NodeMatch { node: matched, env });
+            }
+        }
+        dedup_nested(ret)
+    }
+
+    /// Match once and turn the match into a single text [`Edit`], expanding
+    /// `$X`-style placeholders in `replacement` against the captured `MetaVarEnv`.
+    fn replace<'tree>(
+        &self,
+        node: Node<'tree>,
+        replacement: &str,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Edit> {
+        let mut env = MetaVarEnv::new();
+        let matched = self.find_node(node, &mut env, resolver)?;
+        Some(Template::new(replacement).expand(matched, &env))
+    }
+
+    /// Replace every top-level match under `node`, returning edits ordered
+    /// back-to-front so applying them in order never invalidates an earlier
+    /// edit's offsets. `find_node_vec` can keep a match that sits inside
+    /// another match's captured placeholder (see `dedup_nested`), which is
+    /// exactly the case that would make two emitted `Edit`s overlap here, so
+    /// this additionally drops those before expanding the template.
+    fn replace_all<'tree>(
+        &self,
+        node: Node<'tree>,
+        replacement: &str,
+        resolver: Option<&dyn Resolver>,
+    ) -> Vec<Edit> {
+        let template = Template::new(replacement);
+        let mut edits: Vec<Edit> = top_level_matches(self.find_node_vec(node, resolver))
+            .into_iter()
+            .map(|m| template.expand(m.node, &m.env))
+            .collect();
+        edits.sort_by_key(|e| std::cmp::Reverse(e.start_byte));
+        edits
+    }
+}
+
+/// A match produced by [`Matcher::find_node_vec`], pairing the matched node
+/// with the `MetaVarEnv` that was populated while matching it.
+pub struct NodeMatch<'tree> {
+    pub node: Node<'tree>,
+    pub env: MetaVarEnv<'tree>,
+}
+
+/// Breadth-first iterator over a node's descendants, not including the node
+/// itself. Shared by `find_node_vec` (which additionally visits the node
+/// itself) and relational matchers like `Has` that only look below `node`.
+struct Descendants<'tree> {
+    queue: VecDeque<Node<'tree>>,
+}
+
+impl<'tree> Descendants<'tree> {
+    fn new(node: Node<'tree>) -> Self {
+        let mut queue = VecDeque::new();
+        queue.extend(node.children());
+        Self { queue }
+    }
+}
+
+impl<'tree> Iterator for Descendants<'tree> {
+    type Item = Node<'tree>;
+
+    fn next(&mut self) -> Option<Node<'tree>> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children());
+        Some(node)
+    }
+}
+
+/// Sort matches by start offset (longest first on ties) and drop any match
+/// whose range is fully contained in an already-accepted match's range,
+/// unless it sits inside a metavar placeholder the outer match captured.
+fn dedup_nested<'tree>(mut candidates: Vec<NodeMatch<'tree>>) -> Vec<NodeMatch<'tree>> {
+    candidates.sort_by(|a, b| {
+        a.node
+            .start_byte()
+            .cmp(&b.node.start_byte())
+            .then_with(|| b.node.end_byte().cmp(&a.node.end_byte()))
+    });
+    let mut accepted: Vec<NodeMatch<'tree>> = vec![];
+    let mut stack: Vec<usize> = vec![];
+    for cand in candidates {
+        while let Some(&top) = stack.last() {
+            if cand.node.start_byte() >= accepted[top].node.end_byte() {
+                stack.pop();
+            } else {
+                break;
+            }
+        }
+        if let Some(&top) = stack.last() {
+            let nested = cand.node.end_byte() <= accepted[top].node.end_byte();
+            if nested && !in_placeholder(&accepted[top], &cand) {
+                continue;
+            }
+        }
+        stack.push(accepted.len());
+        accepted.push(cand);
+    }
+    accepted
+}
+
+/// Whether `inner` falls entirely within one of the metavar nodes `outer`
+/// captured while matching.
+fn in_placeholder<'tree>(outer: &NodeMatch<'tree>, inner: &NodeMatch<'tree>) -> bool {
+    outer.env.iter().any(|(_, captured)| {
+        captured.start_byte() <= inner.node.start_byte() && inner.node.end_byte() <= captured.end_byte()
+    })
+}
+
+/// Drop every match nested inside another match, with no placeholder
+/// exception. `dedup_nested` keeps a placeholder-nested match on purpose
+/// (useful when a caller wants every match, e.g. for linting), but a
+/// placeholder-nested match still overlaps its outer match's byte range, so
+/// callers that need a disjoint edit set — `replace_all` — filter with this
+/// instead.
+fn top_level_matches<'tree>(mut candidates: Vec<NodeMatch<'tree>>) -> Vec<NodeMatch<'tree>> {
+    candidates.sort_by(|a, b| {
+        a.node
+            .start_byte()
+            .cmp(&b.node.start_byte())
+            .then_with(|| b.node.end_byte().cmp(&a.node.end_byte()))
+    });
+    let mut accepted: Vec<NodeMatch<'tree>> = vec![];
+    for cand in candidates {
+        if let Some(last) = accepted.last() {
+            if cand.node.start_byte() < last.node.end_byte() {
+                continue;
+            }
+        }
+        accepted.push(cand);
+    }
+    accepted
+}
+
+/// A text substitution computed from a match: replace the byte range
+/// `[start_byte, end_byte)` of the source with `inserted_text`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub inserted_text: String,
+}
+
+/// A replacement template written in the same `$X` metavar syntax as
+/// [`Pattern`]. Expanding a template looks each placeholder up in a
+/// `MetaVarEnv` and substitutes the captured node's source text, leaving
+/// literal text and unbound placeholders untouched.
+pub struct Template {
+    raw: String,
+}
+
+impl Template {
+    pub fn new(template: &str) -> Self {
+        Self {
+            raw: template.to_string(),
+        }
+    }
+
+    pub fn expand<'tree>(&self, node: Node<'tree>, env: &MetaVarEnv<'tree>) -> Edit {
+        Edit {
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            inserted_text: self.render(env),
+        }
+    }
+
+    fn render(&self, env: &MetaVarEnv) -> String {
+        let mut out = String::with_capacity(self.raw.len());
+        let bytes = self.raw.as_str();
+        let mut rest = bytes;
+        while let Some(dollar) = rest.find('$') {
+            out.push_str(&rest[..dollar]);
+            let after = &rest[dollar + 1..];
+            let name_len = after
+                .find(|c: char| c != '_' && !c.is_alphanumeric())
+                .unwrap_or(after.len());
+            if name_len == 0 {
+                out.push('$');
+                rest = after;
+                continue;
+            }
+            let name = &after[..name_len];
+            match env.get_match(name) {
+                Some(captured) => out.push_str(&captured.text()),
+                None => {
+                    out.push('$');
+                    out.push_str(name);
+                }
             }
+            rest = &after[name_len..];
         }
-        ret
+        out.push_str(rest);
+        out
     }
 }
 
@@ -42,9 +282,48 @@ impl<S: AsRef<str>> Matcher for S {
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
         let pattern = Pattern::new(self.as_ref());
-        pattern.match_node(node, env)
+        pattern.match_node(node, env, resolver)
+    }
+
+    /// Syntactic sniff of the pattern text's leading construct. This is a
+    /// stopgap until `Pattern` itself exposes the tree-sitter kind of its
+    /// parsed root node (which would cover every pattern, not just the forms
+    /// recognized here); it only kicks in for the handful of statement and
+    /// call shapes patterns in this crate are actually written in.
+    fn root_kind(&self) -> Option<Vec<&'static str>> {
+        let text = self.as_ref().trim_start();
+        if text.starts_with("let ") || text.starts_with("const ") {
+            Some(vec!["lexical_declaration"])
+        } else if text.starts_with("var ") {
+            Some(vec!["variable_declaration"])
+        } else if is_call_pattern(text) {
+            Some(vec!["call_expression"])
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `text` looks like `name(...)` with no leading keyword/operator.
+fn is_call_pattern(text: &str) -> bool {
+    let text = text.trim_end();
+    match text.find('(') {
+        Some(paren) => {
+            let head = &text[..paren];
+            // The argument list opened at `paren` must close at the very end.
+            let mut depth = 0i32;
+            let close = text[paren..].find(|c: char| {
+                depth += (c == '(') as i32 - (c == ')') as i32;
+                depth == 0
+            });
+            !head.is_empty()
+                && head.chars().all(|c| c == '_' || c.is_alphanumeric())
+                && close.map(|i| paren + i + 1) == Some(text.len())
+        }
+        None => false,
     }
 }
 
@@ -76,9 +355,16 @@ where
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
-        let node = self.pattern1.match_node(node, env)?;
-        self.pattern2.match_node(node, env)
+        let node = self.pattern1.match_node(node, env, resolver)?;
+        self.pattern2.match_node(node, env, resolver)
+    }
+
+    fn root_kind(&self) -> Option<Vec<&'static str>> {
+        // Only the positive side of an `And` constrains the root node kind;
+        // `pattern2` may be a filtering predicate like `Not`/`Inside`.
+        self.pattern1.root_kind()
     }
 }
 
@@ -96,10 +382,20 @@ where
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
         self.pattern1
-            .match_node(node, env)
-            .or_else(|| self.pattern2.match_node(node, env))
+            .match_node(node, env, resolver)
+            .or_else(|| self.pattern2.match_node(node, env, resolver))
+    }
+
+    fn root_kind(&self) -> Option<Vec<&'static str>> {
+        // Either branch could match, so the candidate kind must be acceptable
+        // to at least one of them; fall back to the exhaustive walk if either
+        // branch can't pin down a kind.
+        let mut kinds = self.pattern1.root_kind()?;
+        kinds.extend(self.pattern2.root_kind()?);
+        Some(kinds)
     }
 }
 
@@ -119,10 +415,11 @@ impl Matcher for Inside {
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
         let mut n = node;
         while let Some(p) = n.parent() {
-            if self.outer.match_node(p, env).is_some() {
+            if self.outer.match_node(p, env, resolver).is_some() {
                 return Some(node);
             }
             n = p;
@@ -140,10 +437,11 @@ impl Matcher for NotInside {
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
         let mut n = node;
         while let Some(p) = n.parent() {
-            if self.outer.match_node(p, env).is_some() {
+            if self.outer.match_node(p, env, resolver).is_some() {
                 return None;
             }
             n = p;
@@ -152,6 +450,119 @@ impl Matcher for NotInside {
     }
 }
 
+pub struct Has {
+    inner: Pattern,
+}
+
+impl Matcher for Has {
+    fn match_node<'tree>(
+        &self,
+        node: Node<'tree>,
+        env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Node<'tree>> {
+        for cand in Descendants::new(node) {
+            let mut child_env = MetaVarEnv::new();
+            if self.inner.match_node(cand, &mut child_env, resolver).is_some() {
+                env.merge(child_env);
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+pub struct Follows {
+    prev: Pattern,
+}
+
+impl Matcher for Follows {
+    fn match_node<'tree>(
+        &self,
+        node: Node<'tree>,
+        env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Node<'tree>> {
+        let sibling = node.prev_sibling()?;
+        let mut child_env = MetaVarEnv::new();
+        self.prev.match_node(sibling, &mut child_env, resolver)?;
+        env.merge(child_env);
+        Some(node)
+    }
+}
+
+pub struct Precedes {
+    next: Pattern,
+}
+
+impl Matcher for Precedes {
+    fn match_node<'tree>(
+        &self,
+        node: Node<'tree>,
+        env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Node<'tree>> {
+        let sibling = node.next_sibling()?;
+        let mut child_env = MetaVarEnv::new();
+        self.next.match_node(sibling, &mut child_env, resolver)?;
+        env.merge(child_env);
+        Some(node)
+    }
+}
+
+/// Matches any node `resolver` can resolve to a concrete declaration,
+/// filtering out unresolved/undeclared names. Never matches when no
+/// resolver is supplied, since there is nothing but spelling to fall back
+/// on and spelling alone can't tell "resolved" from "unresolved".
+pub struct Resolved;
+
+impl Matcher for Resolved {
+    fn match_node<'tree>(
+        &self,
+        node: Node<'tree>,
+        _env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Node<'tree>> {
+        resolver?.resolve(node).map(|_| node)
+    }
+}
+
+impl PositiveMatcher for Resolved {}
+
+/// Matches any node that `resolver` resolves to the same symbol as `target`,
+/// regardless of spelling — e.g. letting a rule built around the identifier
+/// in `foo()` also match an occurrence spelled `bar::foo()` elsewhere in the
+/// tree, as long as both resolve to the same declaration. `target` is
+/// produced by resolving some reference node up front (typically the
+/// identifier inside the pattern the rule is modeled on). Combined with that
+/// pattern through `And`, it additionally requires the pattern's match to
+/// resolve to the same symbol; it does not let the pattern match other
+/// spellings. Never matches when no resolver is supplied,
+/// for the same reason `Resolved` doesn't.
+pub struct SameSymbol {
+    target: SymbolId,
+}
+
+impl SameSymbol {
+    pub fn new(target: SymbolId) -> Self {
+        Self { target }
+    }
+}
+
+impl Matcher for SameSymbol {
+    fn match_node<'tree>(
+        &self,
+        node: Node<'tree>,
+        _env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Node<'tree>> {
+        let resolved = resolver?.resolve(node)?;
+        (resolved == self.target).then_some(node)
+    }
+}
+
+impl PositiveMatcher for SameSymbol {}
+
 pub struct Not<P: PositiveMatcher> {
     not: P,
 }
@@ -164,8 +575,9 @@ where
         &self,
         node: Node<'tree>,
         env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
     ) -> Option<Node<'tree>> {
-        if self.not.match_node(node, env).is_none() {
+        if self.not.match_node(node, env, resolver).is_none() {
             Some(node)
         } else {
             None
@@ -173,6 +585,51 @@ where
     }
 }
 
+/// Attaches constraint matchers to individual metavariables captured by
+/// `matcher`. A match only succeeds if, for every constrained name, the
+/// captured node also matches the corresponding constraint; metavars the
+/// constraint captures are merged back into the outer `MetaVarEnv` so they
+/// can be used downstream (e.g. in a replacement template).
+pub struct Constrain<M: Matcher> {
+    matcher: M,
+    constraints: HashMap<String, Box<dyn Matcher>>,
+}
+
+impl<M: Matcher> Constrain<M> {
+    pub fn new(matcher: M) -> Self {
+        Self {
+            matcher,
+            constraints: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, meta_var: &str, constraint: impl Matcher + 'static) -> Self {
+        self.constraints
+            .insert(meta_var.to_string(), Box::new(constraint));
+        self
+    }
+}
+
+impl<M: Matcher> Matcher for Constrain<M> {
+    fn match_node<'tree>(
+        &self,
+        node: Node<'tree>,
+        env: &mut MetaVarEnv<'tree>,
+        resolver: Option<&dyn Resolver>,
+    ) -> Option<Node<'tree>> {
+        let matched = self.matcher.match_node(node, env, resolver)?;
+        for (name, constraint) in &self.constraints {
+            let captured = *env.get_match(name)?;
+            let mut child_env = MetaVarEnv::new();
+            constraint.match_node(captured, &mut child_env, resolver)?;
+            env.merge(child_env);
+        }
+        Some(matched)
+    }
+}
+
+impl<M: PositiveMatcher> PositiveMatcher for Constrain<M> {}
+
 pub struct Rule<M: Matcher> {
     inner: M,
 }
@@ -251,12 +708,12 @@ mod test {
     fn test_find(rule: &impl Matcher, code: &str) {
         let mut env = MetaVarEnv::new();
         let node = Root::new(code);
-        assert!(rule.find_node(node.root(), &mut env).is_some());
+        assert!(rule.find_node(node.root(), &mut env, None).is_some());
     }
     fn test_not_find(rule: &impl Matcher, code: &str) {
         let mut env = MetaVarEnv::new();
         let node = Root::new(code);
-        assert!(rule.find_node(node.root(), &mut env).is_none());
+        assert!(rule.find_node(node.root(), &mut env, None).is_none());
     }
 
     #[test]
@@ -314,4 +771,290 @@ mod test {
         test_not_find(&rule, "let b = 2");
         test_not_find(&rule, "const b = 1");
     }
+
+    struct KindOnly(&'static str);
+    impl Matcher for KindOnly {
+        fn match_node<'tree>(
+            &self,
+            node: Node<'tree>,
+            _env: &mut MetaVarEnv<'tree>,
+            _resolver: Option<&dyn Resolver>,
+        ) -> Option<Node<'tree>> {
+            Some(node)
+        }
+        fn root_kind(&self) -> Option<Vec<&'static str>> {
+            Some(vec![self.0])
+        }
+    }
+    impl PositiveMatcher for KindOnly {}
+
+    use std::cell::Cell;
+
+    /// Wraps a real matcher and counts how many times `match_node` is
+    /// actually invoked, so the kind fast path's effect on `find_node_vec`
+    /// can be measured instead of merely asserted.
+    struct Counting<M: Matcher> {
+        inner: M,
+        calls: Cell<usize>,
+    }
+    impl<M: Matcher> Counting<M> {
+        fn new(inner: M) -> Self {
+            Self {
+                inner,
+                calls: Cell::new(0),
+            }
+        }
+    }
+    impl<M: Matcher> Matcher for Counting<M> {
+        fn match_node<'tree>(
+            &self,
+            node: Node<'tree>,
+            env: &mut MetaVarEnv<'tree>,
+            resolver: Option<&dyn Resolver>,
+        ) -> Option<Node<'tree>> {
+            self.calls.set(self.calls.get() + 1);
+            self.inner.match_node(node, env, resolver)
+        }
+        fn root_kind(&self) -> Option<Vec<&'static str>> {
+            self.inner.root_kind()
+        }
+    }
+
+    fn count_nodes(node: Node) -> usize {
+        1 + Descendants::new(node).count()
+    }
+
+    #[test]
+    fn test_find_node_vec_fast_path_skips_match_attempts() {
+        let probe = Counting::new("foo($X)");
+        let code = "let foo = 1; foo(2);";
+        let root = Root::new(code);
+        let matches = probe.find_node_vec(root.root(), None);
+        assert_eq!(matches.len(), 1);
+        // The `call_expression` kind sniffed from "foo($X)" should let the
+        // fast path skip most of the tree (the `let` declaration and its
+        // descendants) instead of running `match_node` on every node.
+        assert!(probe.calls.get() < count_nodes(root.root()));
+    }
+
+    #[test]
+    fn test_and_root_kind_uses_positive_side() {
+        let rule = And {
+            pattern1: KindOnly("call_expression"),
+            pattern2: KindOnly("identifier"),
+        };
+        assert_eq!(rule.root_kind(), Some(vec!["call_expression"]));
+    }
+
+    #[test]
+    fn test_or_root_kind_unions_branches() {
+        let rule = Or {
+            pattern1: KindOnly("call_expression"),
+            pattern2: KindOnly("identifier"),
+        };
+        assert_eq!(
+            rule.root_kind(),
+            Some(vec!["call_expression", "identifier"])
+        );
+    }
+
+    #[test]
+    fn test_find_node_vec_dedup_nested() {
+        // `$_` is a wildcard and captures nothing, so the inner `foo(1)` is a
+        // plain duplicate of part of the outer match and should be dropped.
+        let pattern = Pattern::new("foo($_)");
+        let node = Root::new("foo(foo(1))");
+        let matches = pattern.find_node_vec(node.root(), None);
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[test]
+    fn test_find_node_vec_keeps_match_inside_placeholder() {
+        // `$X` captures the inner `foo(1)` exactly, so the inner match sits
+        // inside the outer match's own placeholder and is kept.
+        let pattern = Pattern::new("foo($X)");
+        let node = Root::new("foo(foo(1))");
+        let matches = pattern.find_node_vec(node.root(), None);
+        assert_eq!(matches.len(), 2);
+    }
+
+    #[test]
+    fn test_has() {
+        let rule = Has {
+            inner: Pattern::new("foo()"),
+        };
+        test_find(&rule, "bar(foo())");
+        test_not_find(&rule, "bar(baz())");
+    }
+
+    #[test]
+    fn test_follows() {
+        let rule = Follows {
+            prev: Pattern::new("let a = 1"),
+        };
+        test_find(&rule, "let a = 1; let b = 2;");
+        test_not_find(&rule, "let b = 2; let a = 1;");
+    }
+
+    #[test]
+    fn test_precedes() {
+        let rule = Precedes {
+            next: Pattern::new("let b = 2"),
+        };
+        test_find(&rule, "let a = 1; let b = 2;");
+        test_not_find(&rule, "let b = 2; let a = 1;");
+    }
+
+    struct ResolverProbe;
+    impl Matcher for ResolverProbe {
+        fn match_node<'tree>(
+            &self,
+            node: Node<'tree>,
+            _env: &mut MetaVarEnv<'tree>,
+            resolver: Option<&dyn Resolver>,
+        ) -> Option<Node<'tree>> {
+            resolver.is_some().then_some(node)
+        }
+    }
+    impl PositiveMatcher for ResolverProbe {}
+
+    struct StubResolver;
+    impl Resolver for StubResolver {
+        fn resolve<'tree>(&self, _node: Node<'tree>) -> Option<SymbolId> {
+            Some(SymbolId(0))
+        }
+    }
+
+    /// A toy resolver: `foo` and its alias `bar` both resolve to the same
+    /// declaration; anything else is unresolved.
+    struct AliasResolver;
+    impl Resolver for AliasResolver {
+        fn resolve<'tree>(&self, node: Node<'tree>) -> Option<SymbolId> {
+            match node.text().as_ref() {
+                "foo" | "bar" => Some(SymbolId(1)),
+                _ => None,
+            }
+        }
+    }
+
+    fn find_identifier(node: Node) -> Node {
+        Descendants::new(node)
+            .find(|n| n.kind() == "identifier")
+            .expect("no identifier in tree")
+    }
+
+    #[test]
+    fn test_resolver_unifies_differently_spelled_same_symbol_nodes() {
+        let resolver = AliasResolver;
+        let foo_call = Root::new("foo()");
+        let bar_call = Root::new("bar()");
+        let foo_ident = find_identifier(foo_call.root());
+        let bar_ident = find_identifier(bar_call.root());
+        // Different spelling, same resolved symbol.
+        assert_eq!(resolver.resolve(foo_ident), resolver.resolve(bar_ident));
+    }
+
+    #[test]
+    fn test_resolved_matcher() {
+        let node = Root::new("foo()");
+        let ident = find_identifier(node.root());
+        let mut env = MetaVarEnv::new();
+        // No resolver supplied: nothing but spelling to go on, so it never matches.
+        assert!(Resolved.match_node(ident, &mut env, None).is_none());
+        let resolver = AliasResolver;
+        assert!(Resolved
+            .match_node(ident, &mut env, Some(&resolver))
+            .is_some());
+    }
+
+    #[test]
+    fn test_same_symbol_matches_despite_different_spelling() {
+        let resolver = AliasResolver;
+        let foo_call = Root::new("foo()");
+        let foo_ident = find_identifier(foo_call.root());
+        let target = resolver.resolve(foo_ident).expect("foo should resolve");
+        let rule = SameSymbol::new(target);
+
+        // `bar` is an alias for the same declaration as `foo`, so a rule
+        // built around `foo`'s resolved symbol also matches `bar`, despite
+        // the different spelling.
+        let bar_call = Root::new("bar()");
+        let bar_ident = find_identifier(bar_call.root());
+        let mut env = MetaVarEnv::new();
+        assert!(rule
+            .match_node(bar_ident, &mut env, Some(&resolver))
+            .is_some());
+
+        // `baz` doesn't resolve to anything under `AliasResolver`, so it
+        // doesn't match even though it's a different identifier again.
+        let baz_call = Root::new("baz()");
+        let baz_ident = find_identifier(baz_call.root());
+        assert!(rule
+            .match_node(baz_ident, &mut env, Some(&resolver))
+            .is_none());
+    }
+
+    #[test]
+    fn test_resolver_threaded_through_and() {
+        let rule = And {
+            pattern1: ResolverProbe,
+            pattern2: ResolverProbe,
+        };
+        let mut env = MetaVarEnv::new();
+        let node = Root::new("let a = 1");
+        let resolver = StubResolver;
+        assert!(rule
+            .find_node(node.root(), &mut env, Some(&resolver))
+            .is_some());
+        assert!(rule.find_node(node.root(), &mut env, None).is_none());
+    }
+
+    #[test]
+    fn test_constrain() {
+        let rule = Constrain::new(Pattern::new("let $X = $Y")).with("Y", Pattern::new("foo($_)"));
+        test_find(&rule, "let a = foo(1)");
+        test_not_find(&rule, "let a = 1");
+        test_not_find(&rule, "let a = bar(1)");
+    }
+
+    #[test]
+    fn test_replace() {
+        let pattern = Pattern::new("let a = $X");
+        let node = Root::new("let a = 233");
+        let edit = pattern.replace(node.root(), "let b = $X", None).unwrap();
+        assert_eq!(edit.inserted_text, "let b = 233");
+    }
+
+    #[test]
+    fn test_replace_no_match() {
+        let pattern = Pattern::new("let a = $X");
+        let node = Root::new("const a = 233");
+        assert!(pattern.replace(node.root(), "let b = $X", None).is_none());
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let pattern = Pattern::new("foo($X)");
+        let node = Root::new("foo(1); foo(2);");
+        let edits = pattern.replace_all(node.root(), "bar($X)", None);
+        assert_eq!(edits.len(), 2);
+        // back-to-front: the later match comes first
+        assert!(edits[0].start_byte > edits[1].start_byte);
+        assert_eq!(edits[0].inserted_text, "bar(2)");
+        assert_eq!(edits[1].inserted_text, "bar(1)");
+    }
+
+    #[test]
+    fn test_replace_all_drops_placeholder_nested_match() {
+        // `find_node_vec` keeps both the outer `foo(foo(1))` match and the
+        // inner `foo(1)` match (it sits inside the outer's `$X` placeholder),
+        // but emitting both as edits would overlap: replacing the outer range
+        // already rewrites the inner one. `replace_all` must emit only the
+        // outer, disjoint edit.
+        let pattern = Pattern::new("foo($X)");
+        let node = Root::new("foo(foo(1))");
+        let edits = pattern.replace_all(node.root(), "bar($X)", None);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].inserted_text, "bar(foo(1))");
+    }
 }
\ No newline at end of file